@@ -0,0 +1,106 @@
+//! Accented-Latin-to-ASCII normalization used to make fuzzy matching
+//! diacritic-insensitive, e.g. so a plain `"resume"` query can find
+//! `"résumé"`.
+//!
+//! This is a hand-maintained table of common Western/Central European
+//! accented letters rather than a full Unicode NFD decomposition (no
+//! normalization crate is available to this utility), so obscure scripts may
+//! not be covered.
+
+/// Controls whether accented Latin characters in the haystack are normalized
+/// to their base ASCII form before matching.
+///
+/// Stripping only happens when matching is case-insensitive; when the
+/// [`crate::CaseSensitivity`] setting makes a match case-sensitive, chars are
+/// compared as they are under every mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiacriticsMode {
+    /// Never strip diacritics; matching is purely literal (after case
+    /// folding).
+    Literal,
+    /// Always strip diacritics from the haystack (and needle) before
+    /// matching.
+    AlwaysStrip,
+    /// Strip diacritics from the haystack only when the needle itself has
+    /// none, so an accented query still matches exactly rather than also
+    /// matching unrelated accented letters.
+    #[default]
+    Smart,
+}
+
+/// Returns the ASCII replacement for a (lowercased) accented char, or `None`
+/// if `ch` needs no normalization.
+fn diacritic_replacement(ch: char) -> Option<&'static str> {
+    Some(match ch {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => "e",
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' => "i",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => "o",
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => "u",
+        'ý' | 'ÿ' => "y",
+        'ñ' | 'ń' | 'ņ' | 'ň' => "n",
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => "c",
+        'ß' => "ss",
+        'æ' => "ae",
+        'œ' => "oe",
+        'ð' => "d",
+        'þ' => "th",
+        'ś' | 'ş' | 'š' => "s",
+        'ź' | 'ż' | 'ž' => "z",
+        'ł' => "l",
+        'ř' => "r",
+        'ť' => "t",
+        'ď' => "d",
+        'ľ' | 'ĺ' => "l",
+        _ => return None,
+    })
+}
+
+/// Appends the diacritic-stripped form of `ch` to `out` (or `ch` itself if it
+/// needs no stripping).
+pub(crate) fn push_diacritic_stripped(ch: char, out: &mut Vec<char>) {
+    match diacritic_replacement(ch) {
+        Some(replacement) => out.extend(replacement.chars()),
+        None => out.push(ch),
+    }
+}
+
+/// Whether `needle` contains any char that diacritic stripping would change.
+pub(crate) fn needle_has_diacritics(needle: &str) -> bool {
+    needle
+        .chars()
+        .flat_map(|c| c.to_lowercase())
+        .any(|lc| diacritic_replacement(lc).is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_common_accents() {
+        let mut out = Vec::new();
+        push_diacritic_stripped('é', &mut out);
+        assert_eq!(out, vec!['e']);
+    }
+
+    #[test]
+    fn expands_sharp_s_to_two_chars() {
+        let mut out = Vec::new();
+        push_diacritic_stripped('ß', &mut out);
+        assert_eq!(out, vec!['s', 's']);
+    }
+
+    #[test]
+    fn leaves_plain_ascii_untouched() {
+        let mut out = Vec::new();
+        push_diacritic_stripped('e', &mut out);
+        assert_eq!(out, vec!['e']);
+    }
+
+    #[test]
+    fn detects_diacritics_in_needle() {
+        assert!(needle_has_diacritics("résumé"));
+        assert!(!needle_has_diacritics("resume"));
+    }
+}
@@ -1,6 +1,6 @@
 //! # Fuzzy Match Utility
 //!
-//! This module provides a simple case-insensitive subsequence matcher used for
+//! This module provides a case-insensitive subsequence matcher used for
 //! fuzzy filtering in various parts of the Codex TUI, such as skill search and
 //! slash command filtering.
 //!
@@ -12,11 +12,196 @@
 //!
 //! ## Design Patterns
 //!
-//! - **Greedy Matching**: The algorithm uses a simple greedy approach to find the
-//!   first occurrence of each character in the needle within the haystack.
+//! - **Two-Phase Matching**: For haystacks up to [`OPTIMAL_HAYSTACK_CHAR_LIMIT`]
+//!   chars, an optimal alignment score is computed via dynamic programming,
+//!   modeled on the fzf v2 algorithm. Beyond that limit the matcher falls back
+//!   to a linear-time greedy subsequence scan so very large haystacks stay
+//!   cheap to filter.
 //! - **Unicode Mapping**: To handle Unicode correctly (especially characters that
 //!   expand when lowercased), it maintains an explicit mapping between normalized
 //!   characters and their original source indices.
+//! - **Char-Class Bonuses**: Matches that land on a word boundary (after a
+//!   delimiter) or a camelCase hump score higher than scattered mid-word hits;
+//!   see [`CharClassConfig`] to customize the delimiter set.
+//! - **Query Patterns**: [`Pattern`] layers fzf-style multi-atom query syntax
+//!   (`^prefix`, `suffix$`, `'exact`, `!negate`) on top of `fuzzy_match` for
+//!   callers that want users to type structured queries.
+//! - **Diacritics**: By default ([`DiacriticsMode::Smart`]), a plain ASCII
+//!   needle also matches accented haystack chars (`"resume"` finds
+//!   `"résumé"`), while an accented needle still matches exactly.
+//! - **Configurable Matching**: [`MatcherConfig`] bundles case sensitivity
+//!   ([`CaseSensitivity`]), char classes, diacritics, and prefix preference
+//!   into a single value passed to [`fuzzy_match_with_config`], so callers
+//!   like autocompletion (see [`MatcherConfig::autocomplete`]) can tune
+//!   ranking without distorting general fuzzy matching.
+
+mod diacritics;
+mod pattern;
+
+use diacritics::push_diacritic_stripped;
+use diacritics::needle_has_diacritics;
+
+pub use diacritics::DiacriticsMode;
+pub use pattern::Pattern;
+
+/// Haystacks with more normalized chars than this use the linear-time greedy
+/// scan instead of the `O(M*N)` optimal alignment, to keep matching large
+/// candidate lists cheap.
+const OPTIMAL_HAYSTACK_CHAR_LIMIT: usize = 1024;
+
+/// Base score awarded for each matched needle character.
+const SCORE_MATCH: i32 = 16;
+
+/// Bonus added per extra character in a run of consecutive matches, on top of
+/// `SCORE_MATCH`, to reward contiguous spans over scattered ones.
+const SCORE_CONSECUTIVE: i32 = 8;
+
+/// Penalty applied for each haystack character skipped while searching for
+/// the next needle character.
+const SCORE_GAP_PENALTY: i32 = -3;
+
+/// Bonus added when a needle character matches at a word boundary (the very
+/// start of the haystack, or right after a delimiter), to prefer matches that
+/// line up with how a human would read the haystack.
+const BONUS_BOUNDARY: i32 = 8;
+
+/// Bonus added when a match lands on a `lower -> Upper` camelCase hump, e.g.
+/// the `B` in `fooBar`. A hump is not a delimiter boundary, so it does not
+/// also get [`BONUS_BOUNDARY`].
+const BONUS_CAMEL: i32 = 6;
+
+/// Extra bonus for the very first matched needle character landing on an
+/// `Upper` or `Number` haystack char, to favor acronym-style matches (e.g.
+/// picking out the capitals in `HTTPClient`) over scattered mid-word hits.
+const BONUS_FIRST_CHAR_CLASS: i32 = 4;
+
+/// Cap on the [`MatcherConfig::prefer_prefix`] bonus, applied to the very
+/// first matched needle character and decaying to zero as its haystack
+/// offset grows. Kept smaller in magnitude than [`SCORE_GAP_PENALTY`] so it
+/// only breaks ties between otherwise-equal matches rather than pulling a
+/// needle away from a genuinely tighter match further into the haystack.
+const PREFER_PREFIX_BONUS_CAP: i32 = 2;
+
+/// Classification of a single haystack character, used to detect word
+/// boundaries and camelCase humps for scoring bonuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CharClass {
+    Lower,
+    Upper,
+    Number,
+    Delimiter,
+    NonWord,
+}
+
+/// Controls which haystack characters count as word-boundary delimiters when
+/// computing boundary bonuses.
+///
+/// Args:
+///     None: constructed via [`CharClassConfig::default`] or a preset.
+///
+/// Example:
+///
+/// ```rust
+/// use codex_utils_fuzzy_match::CharClassConfig;
+///
+/// let config = CharClassConfig::match_paths();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CharClassConfig {
+    delimiters: Vec<char>,
+}
+
+impl Default for CharClassConfig {
+    /// General-purpose delimiter set covering common identifier and
+    /// filename separators.
+    fn default() -> Self {
+        Self {
+            delimiters: vec![' ', '_', '-', '.', '/'],
+        }
+    }
+}
+
+impl CharClassConfig {
+    /// Preset tuned for matching filesystem paths, where `/` is the dominant
+    /// word boundary and other punctuation (`.`, `-`, `_`) is left as part of
+    /// the surrounding filename rather than treated as a delimiter.
+    pub fn match_paths() -> Self {
+        Self {
+            delimiters: vec!['/'],
+        }
+    }
+
+    fn classify(&self, ch: char) -> CharClass {
+        if ch.is_whitespace() || self.delimiters.contains(&ch) {
+            CharClass::Delimiter
+        } else if ch.is_ascii_digit() {
+            CharClass::Number
+        } else if ch.is_uppercase() {
+            CharClass::Upper
+        } else if ch.is_lowercase() {
+            CharClass::Lower
+        } else {
+            CharClass::NonWord
+        }
+    }
+}
+
+/// Controls how needle/haystack case differences are treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CaseSensitivity {
+    /// Always match case-insensitively (the historical `fuzzy_match`
+    /// behavior).
+    #[default]
+    Ignore,
+    /// Always match case-sensitively.
+    Respect,
+    /// Case-sensitive only if `needle` itself contains an uppercase
+    /// character, mirroring the "smart case" convention of tools like
+    /// ripgrep: a lowercase query stays permissive, but typing a capital
+    /// signals the user cares about case.
+    Smart,
+}
+
+/// Bundles every tunable knob accepted by [`fuzzy_match_with_config`]: case
+/// sensitivity, word-boundary char classes, diacritic normalization, and
+/// prefix preference.
+///
+/// Args:
+///     None: constructed via [`MatcherConfig::default`] or a preset like
+///     [`MatcherConfig::autocomplete`].
+#[derive(Debug, Clone, Default)]
+pub struct MatcherConfig {
+    pub ignore_case: CaseSensitivity,
+    pub char_classes: CharClassConfig,
+    pub diacritics: DiacriticsMode,
+    /// When `true`, the first matched needle character gets a small bonus
+    /// (see [`PREFER_PREFIX_BONUS_CAP`]) that decays as its distance from the
+    /// start of the haystack grows, favoring matches anchored near the
+    /// front. Off by default since it's a niche preference for
+    /// autocompletion-style callers, not general-purpose fuzzy ranking.
+    pub prefer_prefix: bool,
+}
+
+impl MatcherConfig {
+    /// Preset tuned for autocompletion, where the user types a leading
+    /// fragment and matches anchored near the start of the haystack should
+    /// be favored over otherwise-equal matches further in.
+    ///
+    /// Example:
+    ///
+    /// ```rust
+    /// use codex_utils_fuzzy_match::{fuzzy_match_with_config, MatcherConfig};
+    ///
+    /// let config = MatcherConfig::autocomplete();
+    /// assert!(fuzzy_match_with_config("help", "he", &config).is_some());
+    /// ```
+    pub fn autocomplete() -> Self {
+        Self {
+            prefer_prefix: true,
+            ..Self::default()
+        }
+    }
+}
 
 /// Performs a case-insensitive fuzzy match of a needle against a haystack.
 ///
@@ -32,19 +217,25 @@
 ///     Option<(Vec<usize>, i32)>:
 ///         Some((indices, score)):
 ///             indices: A sorted list of unique character positions in the original haystack.
-///             score: A ranking metric where smaller is better.
+///             score: A ranking metric where larger is better.
 ///         None: If no match is found.
 ///
 /// Logic:
-///     The algorithm performs a greedy subsequence search after normalizing both
-///     inputs to lowercase. It maintains an internal mapping from normalized
-///     character positions back to original `haystack` indices to handle Unicode
-///     characters that expand during lowercasing (e.g., 'İ' expanding to 'i' + '̇').
+///     Both inputs are normalized to lowercase, and an internal mapping from
+///     normalized character positions back to original `haystack` indices is
+///     kept to handle Unicode characters that expand during lowercasing (e.g.,
+///     'İ' expanding to 'i' + '̇'). When the normalized haystack is short enough
+///     (see [`OPTIMAL_HAYSTACK_CHAR_LIMIT`]), an optimal alignment is computed
+///     via dynamic programming so the best-scoring subsequence is always
+///     chosen (e.g. picking the right `l` in `"hello"`). Larger haystacks fall
+///     back to a greedy left-to-right scan to stay linear time.
 ///
 /// Complexity:
-///     Time Complexity: O(N + M), where N is the length of the haystack and M
-///         is the length of the needle.
-///     Space Complexity: O(N + M) for storing normalized characters and mappings.
+///     Time Complexity: O(N + M) for the greedy fallback; O(M*N) for the
+///         optimal alignment path, where N is the length of the haystack and
+///         M is the length of the needle.
+///     Space Complexity: O(N + M) for the greedy fallback; O(M*N) for the
+///         optimal alignment path.
 ///
 /// Exceptions:
 ///     - Returns `Some((Vec::new(), i32::MAX))` if the needle is empty.
@@ -55,66 +246,528 @@
 /// ```rust
 /// use codex_utils_fuzzy_match::fuzzy_match;
 ///
-/// let (indices, score) = fuzzy_match("hello", "hl").unwrap();
+/// let (indices, _score) = fuzzy_match("hello", "hl").unwrap();
 /// assert_eq!(indices, vec![0, 2]);
 /// ```
 pub fn fuzzy_match(haystack: &str, needle: &str) -> Option<(Vec<usize>, i32)> {
+    fuzzy_match_with_config(haystack, needle, &MatcherConfig::default())
+}
+
+/// Same as [`fuzzy_match`], but every scoring knob is controlled by a
+/// caller-supplied [`MatcherConfig`] (case sensitivity, char classes,
+/// diacritics, and prefix preference).
+pub fn fuzzy_match_with_config(
+    haystack: &str,
+    needle: &str,
+    config: &MatcherConfig,
+) -> Option<(Vec<usize>, i32)> {
     if needle.is_empty() {
         return Some((Vec::new(), i32::MAX));
     }
 
+    let case_sensitive = is_case_sensitive(config.ignore_case, needle);
+    let strip_haystack_diacritics =
+        !case_sensitive && should_strip_diacritics(config.diacritics, needle);
+
     let mut lowered_chars: Vec<char> = Vec::new();
+    let mut case_chars: Vec<char> = Vec::new();
     let mut lowered_to_orig_char_idx: Vec<usize> = Vec::new();
-    for (orig_idx, ch) in haystack.chars().enumerate() {
+    normalize_haystack(
+        haystack,
+        case_sensitive,
+        strip_haystack_diacritics,
+        &mut lowered_chars,
+        &mut case_chars,
+        &mut lowered_to_orig_char_idx,
+    );
+
+    let lowered_needle = normalize_needle(
+        needle,
+        case_sensitive,
+        !case_sensitive && config.diacritics == DiacriticsMode::AlwaysStrip,
+    );
+
+    let (positions, score) = match_normalized(&lowered_chars, &case_chars, &lowered_needle, config)?;
+
+    Some(into_orig_indices(positions, &lowered_to_orig_char_idx, score))
+}
+
+/// Decides whether `needle` should be matched case-sensitively under `mode`.
+pub(crate) fn is_case_sensitive(mode: CaseSensitivity, needle: &str) -> bool {
+    match mode {
+        CaseSensitivity::Ignore => false,
+        CaseSensitivity::Respect => true,
+        CaseSensitivity::Smart => needle.chars().any(|c| c.is_uppercase()),
+    }
+}
+
+/// Decides whether the haystack should have diacritics stripped before
+/// matching, given the requested mode and the needle being searched for.
+pub(crate) fn should_strip_diacritics(mode: DiacriticsMode, needle: &str) -> bool {
+    match mode {
+        DiacriticsMode::Literal => false,
+        DiacriticsMode::AlwaysStrip => true,
+        DiacriticsMode::Smart => !needle_has_diacritics(needle),
+    }
+}
+
+/// Normalizes `needle` the same way [`normalize_haystack`] normalizes the
+/// haystack: lowercased unless `case_sensitive`, optionally also stripping
+/// diacritics (see [`DiacriticsMode::AlwaysStrip`]).
+pub(crate) fn normalize_needle(needle: &str, case_sensitive: bool, strip_diacritics: bool) -> Vec<char> {
+    let mut lowered_needle = Vec::with_capacity(needle.len());
+    for ch in needle.chars() {
+        if case_sensitive {
+            lowered_needle.push(ch);
+            continue;
+        }
         for lc in ch.to_lowercase() {
-            lowered_chars.push(lc);
+            if strip_diacritics {
+                push_diacritic_stripped(lc, &mut lowered_needle);
+            } else {
+                lowered_needle.push(lc);
+            }
+        }
+    }
+    lowered_needle
+}
+
+/// Filters `candidates` down to those that fuzzy-match `needle`, sorted
+/// best-first.
+///
+/// Args:
+///     candidates (impl IntoIterator<Item = &'a str>): The candidates to
+///         filter, e.g. skill or slash-command names.
+///     needle (&str): The query to match each candidate against.
+///
+/// Returns:
+///     Vec<(&'a str, Vec<usize>, i32)>: The matching candidates (`None`
+///     results from `fuzzy_match` are dropped), sorted by score descending,
+///     then by haystack length ascending, then lexicographically, so ties are
+///     broken deterministically.
+///
+/// Note:
+///     Candidates longer than [`OPTIMAL_HAYSTACK_CHAR_LIMIT`] are scored by
+///     the linear-time greedy fallback instead of the optimal alignment. It
+///     uses the same per-char bonus/penalty terms so scores stay on the same
+///     scale, but it isn't guaranteed to find the highest-scoring alignment,
+///     so sorting a list mixing such candidates with short ones may not
+///     reflect the true best-first order as precisely as an all-short list
+///     would.
+///
+/// Example:
+///
+/// ```rust
+/// use codex_utils_fuzzy_match::match_list;
+///
+/// let results = match_list(["help", "skill", "hello"], "hl");
+/// assert_eq!(results[0].0, "help");
+/// ```
+pub fn match_list<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    needle: &str,
+) -> Vec<(&'a str, Vec<usize>, i32)> {
+    match_list_with_config(candidates, needle, &MatcherConfig::default())
+}
+
+/// Same as [`match_list`], but every scoring knob is controlled by a
+/// caller-supplied [`MatcherConfig`].
+pub fn match_list_with_config<'a>(
+    candidates: impl IntoIterator<Item = &'a str>,
+    needle: &str,
+    config: &MatcherConfig,
+) -> Vec<(&'a str, Vec<usize>, i32)> {
+    let case_sensitive = is_case_sensitive(config.ignore_case, needle);
+    let strip_haystack_diacritics =
+        !case_sensitive && should_strip_diacritics(config.diacritics, needle);
+    let lowered_needle = normalize_needle(
+        needle,
+        case_sensitive,
+        !case_sensitive && config.diacritics == DiacriticsMode::AlwaysStrip,
+    );
+
+    // Reused across candidates instead of allocating fresh normalization
+    // buffers per item, since skill/command lists can be large.
+    let mut lowered_chars: Vec<char> = Vec::new();
+    let mut case_chars: Vec<char> = Vec::new();
+    let mut lowered_to_orig_char_idx: Vec<usize> = Vec::new();
+
+    let mut results: Vec<(&'a str, Vec<usize>, i32)> = Vec::new();
+    for candidate in candidates {
+        let matched = if needle.is_empty() {
+            Some((Vec::new(), i32::MAX))
+        } else {
+            normalize_haystack(
+                candidate,
+                case_sensitive,
+                strip_haystack_diacritics,
+                &mut lowered_chars,
+                &mut case_chars,
+                &mut lowered_to_orig_char_idx,
+            );
+            match_normalized(&lowered_chars, &case_chars, &lowered_needle, config)
+        };
+
+        if let Some((positions, score)) = matched {
+            let (indices, score) = into_orig_indices(positions, &lowered_to_orig_char_idx, score);
+            results.push((candidate, indices, score));
+        }
+    }
+
+    results.sort_by(|(a_haystack, _, a_score), (b_haystack, _, b_score)| {
+        b_score
+            .cmp(a_score)
+            .then_with(|| a_haystack.len().cmp(&b_haystack.len()))
+            .then_with(|| a_haystack.cmp(b_haystack))
+    });
+
+    results
+}
+
+/// Normalizes `haystack` into chars for matching (lowercased unless
+/// `case_sensitive`, optionally also stripping diacritics), clearing and then
+/// writing into the given scratch buffers.
+/// Factored out so [`match_list`] can reuse the same buffers across
+/// candidates instead of allocating fresh ones per item.
+pub(crate) fn normalize_haystack(
+    haystack: &str,
+    case_sensitive: bool,
+    strip_diacritics: bool,
+    lowered_chars: &mut Vec<char>,
+    case_chars: &mut Vec<char>,
+    lowered_to_orig_char_idx: &mut Vec<usize>,
+) {
+    lowered_chars.clear();
+    case_chars.clear();
+    lowered_to_orig_char_idx.clear();
+    for (orig_idx, ch) in haystack.chars().enumerate() {
+        if case_sensitive {
+            lowered_chars.push(ch);
+            case_chars.push(ch);
             lowered_to_orig_char_idx.push(orig_idx);
+            continue;
+        }
+        for lc in ch.to_lowercase() {
+            let before = lowered_chars.len();
+            if strip_diacritics {
+                push_diacritic_stripped(lc, lowered_chars);
+            } else {
+                lowered_chars.push(lc);
+            }
+            for _ in before..lowered_chars.len() {
+                case_chars.push(ch);
+                lowered_to_orig_char_idx.push(orig_idx);
+            }
+        }
+    }
+}
+
+/// Runs the optimal-or-greedy matcher over already-normalized haystack chars.
+fn match_normalized(
+    lowered_chars: &[char],
+    case_chars: &[char],
+    lowered_needle: &[char],
+    config: &MatcherConfig,
+) -> Option<(Vec<usize>, i32)> {
+    if lowered_chars.len() <= OPTIMAL_HAYSTACK_CHAR_LIMIT {
+        optimal_match(lowered_chars, case_chars, lowered_needle, config)
+    } else {
+        greedy_match(lowered_chars, case_chars, lowered_needle, config)
+    }
+}
+
+/// Maps matched lowered-char positions back to original `haystack` char
+/// indices, sorting and deduping (an expanding lowercase like 'İ' -> "i̇" can
+/// otherwise produce the same original index twice).
+pub(crate) fn into_orig_indices(
+    positions: Vec<usize>,
+    lowered_to_orig_char_idx: &[usize],
+    score: i32,
+) -> (Vec<usize>, i32) {
+    let mut result_orig_indices: Vec<usize> = positions
+        .into_iter()
+        .map(|pos| lowered_to_orig_char_idx[pos])
+        .collect();
+    result_orig_indices.sort_unstable();
+    result_orig_indices.dedup();
+    (result_orig_indices, score)
+}
+
+/// Computes the optimal alignment of `needle` within `haystack` via dynamic
+/// programming, modeled on the fzf v2 scoring algorithm.
+///
+/// Args:
+///     haystack (&[char]): The lowercased haystack characters.
+///     needle (&[char]): The lowercased needle characters.
+///
+/// Returns:
+///     Option<(Vec<usize>, i32)>: The matched positions (into `haystack`) and
+///     the alignment score (larger is better), or `None` if `needle` is not a
+///     subsequence of `haystack`.
+///
+/// Logic:
+///     Fills a `best[i][j]` matrix over needle index `i` and haystack index
+///     `j` using the recurrence
+///     `best[i][j] = max(end[i][j], best[i][j-1] + gap_penalty, 0)`, where
+///     `end[i][j]` is the score of an alignment whose needle[i] match lands
+///     *exactly* at `j` (only defined when `haystack[j] == needle[i]`) and
+///     `gap_penalty` is charged against `best[i][j-1]` whether or not
+///     `haystack[j] == needle[i]`: even when the characters match, using this
+///     occurrence is only one candidate alignment, and skipping it (to prefer
+///     a different occurrence later, e.g. one followed by a consecutive-run
+///     bonus) still pays the same per-char cost as any other skipped
+///     haystack char. `best[i][j]` alone is not enough to track consecutive
+///     runs correctly, since it collapses "the best score through column j"
+///     and "the best score with needle[i] landing at exactly column j" into
+///     one value, which can silently discard a locally-worse-but-globally-
+///     better match (one that scores less on its own but unlocks a larger
+///     consecutive-run bonus for needle[i+1]). So `end[i][j]` is tracked in
+///     its own matrix, computed as the better of two predecessors:
+///     continuing a genuine consecutive run from `end[i-1][j-1]` (when
+///     `haystack[j-1] == needle[i-1]`), or restarting fresh from
+///     `best[i-1][j-1]`. The next row's diagonal transition always reads
+///     from `end[i-1][j-1]`, not the collapsed `best[i-1][j-1]`, so it can
+///     still find a consecutive run through a position that lost out on
+///     `best[i-1][j-1]` to a different, better overall alignment. The best
+///     score in the last needle row is the overall alignment score;
+///     parallel matrices of backpointers (`matched`, `via_consecutive`) are
+///     used to recover the matched indices.
+fn optimal_match(
+    haystack: &[char],
+    case_chars: &[char],
+    needle: &[char],
+    config: &MatcherConfig,
+) -> Option<(Vec<usize>, i32)> {
+    let m = needle.len();
+    let n = haystack.len();
+    if m == 0 || n < m {
+        return None;
+    }
+
+    // `best`/`matched`/`reachable` track the best score through column `j`
+    // (used for gap-penalty carry-forward and the final per-row max).
+    // `end`/`end_run`/`end_defined`/`via_consecutive` track the best score (and run length) of an alignment whose
+    // needle[i] match lands exactly at column `j`, independent of whether
+    // that score wins the `best[i][j]` comparison, so a later row can still
+    // chain a consecutive run through it. `reachable` tracks whether
+    // needle[i] has matched anywhere in haystack[..=j]; it is needed because
+    // a decayed (gap-penalized) score can coincidentally equal the score of
+    // a prefix that never matched at all, so the raw score alone can't tell
+    // a real alignment apart from an infeasible one.
+    let mut best = vec![0i32; m * n];
+    let mut matched = vec![false; m * n];
+    let mut reachable = vec![false; m * n];
+    let mut end = vec![0i32; m * n];
+    let mut end_run = vec![0u32; m * n];
+    let mut end_defined = vec![false; m * n];
+    let mut via_consecutive = vec![false; m * n];
+    let idx = |i: usize, j: usize| i * n + j;
+
+    for i in 0..m {
+        for j in 0..n {
+            let prev_row_reachable = if i == 0 {
+                true
+            } else if j > 0 {
+                reachable[idx(i - 1, j - 1)]
+            } else {
+                false
+            };
+
+            if haystack[j] == needle[i] && prev_row_reachable {
+                let fresh_base = if i > 0 && j > 0 { best[idx(i - 1, j - 1)] } else { 0 };
+                let fresh_bonus = match_bonus(case_chars, j, i == 0, config);
+                let fresh_candidate = fresh_base + fresh_bonus;
+
+                let consecutive_candidate = if i > 0 && j > 0 && end_defined[idx(i - 1, j - 1)] {
+                    let run = end_run[idx(i - 1, j - 1)] + 1;
+                    let bonus = fresh_bonus + (run as i32 - 1) * SCORE_CONSECUTIVE;
+                    Some((end[idx(i - 1, j - 1)] + bonus, run))
+                } else {
+                    None
+                };
+
+                let (end_score, end_run_len, end_via_consec) = match consecutive_candidate {
+                    Some((consec_score, consec_run)) if consec_score >= fresh_candidate => {
+                        (consec_score, consec_run, true)
+                    }
+                    _ => (fresh_candidate, 1, false),
+                };
+
+                end[idx(i, j)] = end_score;
+                end_run[idx(i, j)] = end_run_len;
+                end_defined[idx(i, j)] = true;
+                via_consecutive[idx(i, j)] = end_via_consec;
+
+                let row_reachable_before = j > 0 && reachable[idx(i, j - 1)];
+                let carry_forward = if row_reachable_before {
+                    best[idx(i, j - 1)] + SCORE_GAP_PENALTY
+                } else {
+                    i32::MIN
+                };
+
+                if end_score >= carry_forward {
+                    best[idx(i, j)] = end_score.max(0);
+                    matched[idx(i, j)] = true;
+                } else {
+                    best[idx(i, j)] = carry_forward.max(0);
+                }
+                reachable[idx(i, j)] = true;
+            } else if j > 0 && reachable[idx(i, j - 1)] {
+                best[idx(i, j)] = (best[idx(i, j - 1)] + SCORE_GAP_PENALTY).max(0);
+                reachable[idx(i, j)] = true;
+            }
+        }
+    }
+
+    let last_row = m - 1;
+    let (best_j, &best_score) = (0..n)
+        .filter(|&j| reachable[idx(last_row, j)])
+        .map(|j| (j, &best[idx(last_row, j)]))
+        .max_by_key(|&(_, &s)| s)?;
+
+    let mut positions = vec![0usize; m];
+    let mut i = last_row;
+    let mut j = best_j;
+    // `force` is set right after following a consecutive-run backpointer: it
+    // means needle[i] is *known* to end exactly at `j` (that's what made the
+    // consecutive run possible), even if `matched[idx(i, j)]` is false
+    // because a different, better overall alignment won `best[i][j]`.
+    let mut force = false;
+    loop {
+        if force || matched[idx(i, j)] {
+            positions[i] = j;
+            if i == 0 {
+                break;
+            }
+            let via_consec = via_consecutive[idx(i, j)];
+            i -= 1;
+            if j == 0 {
+                // Needle chars remain but no haystack left; shouldn't happen
+                // since `n >= m`, but guard defensively.
+                return None;
+            }
+            j -= 1;
+            force = via_consec;
+        } else if j == 0 {
+            return None;
+        } else {
+            j -= 1;
         }
     }
 
-    let lowered_needle: Vec<char> = needle.to_lowercase().chars().collect();
+    Some((positions, best_score))
+}
+
+/// Awards a bonus for a needle character matching at haystack position `j`.
+///
+/// Args:
+///     case_chars (&[char]): The haystack characters in their original case,
+///         aligned 1:1 with the lowercased chars used for matching (case is
+///         needed to detect camelCase humps that matching itself ignores).
+///     j (usize): The haystack position being matched.
+///     is_first_needle_char (bool): Whether this is the first needle
+///         character, i.e. the start of the overall match.
+///     config (&MatcherConfig): Controls which haystack chars count as
+///         word-boundary delimiters, and whether a prefix-preference bonus
+///         applies.
+///
+/// Logic:
+///     Rewards matches at word boundaries (start of haystack, or right after
+///     a delimiter), camelCase humps (`lower` immediately followed by
+///     `Upper`), and, for the first matched needle character only, landing on
+///     an `Upper`/`Number` char (to favor acronym-style matches) plus, when
+///     [`MatcherConfig::prefer_prefix`] is set, a bonus that decays to zero as
+///     `j` grows (see [`PREFER_PREFIX_BONUS_CAP`]).
+fn match_bonus(case_chars: &[char], j: usize, is_first_needle_char: bool, config: &MatcherConfig) -> i32 {
+    let mut bonus = SCORE_MATCH;
+    let char_classes = &config.char_classes;
+    let this_class = char_classes.classify(case_chars[j]);
+    let prev_class = if j == 0 {
+        None
+    } else {
+        Some(char_classes.classify(case_chars[j - 1]))
+    };
+
+    if j == 0 || prev_class == Some(CharClass::Delimiter) {
+        bonus += BONUS_BOUNDARY;
+    }
+    if prev_class == Some(CharClass::Lower) && this_class == CharClass::Upper {
+        bonus += BONUS_CAMEL;
+    }
+    if is_first_needle_char && matches!(this_class, CharClass::Upper | CharClass::Number) {
+        bonus += BONUS_FIRST_CHAR_CLASS;
+    }
+    if is_first_needle_char && config.prefer_prefix {
+        bonus += (PREFER_PREFIX_BONUS_CAP - j as i32).max(0);
+    }
+
+    bonus
+}
 
-    let mut result_orig_indices: Vec<usize> = Vec::with_capacity(lowered_needle.len());
-    let mut last_lower_pos: Option<usize> = None;
+/// Performs a greedy left-to-right subsequence scan, used as a fast fallback
+/// for haystacks too large to run the optimal alignment on.
+///
+/// Args:
+///     haystack (&[char]): The lowercased haystack characters.
+///     case_chars (&[char]): The haystack characters in their original case,
+///         aligned 1:1 with `haystack` (see [`match_bonus`]).
+///     needle (&[char]): The lowercased needle characters.
+///     config (&MatcherConfig): Controls char-class and prefix-preference
+///         bonuses, same as [`optimal_match`].
+///
+/// Returns:
+///     Option<(Vec<usize>, i32)>: The matched positions (into `haystack`) and
+///     a score (larger is better), or `None` if `needle` is not a subsequence
+///     of `haystack`.
+///
+/// Logic:
+///     Greedily consumes the first occurrence of each needle character in
+///     order. This can report a worse match window than the optimal path
+///     would (e.g. the first `l` it grabs may force a wider span than a later
+///     one), which is the tradeoff made to keep this path linear time. The
+///     single resulting position sequence is then scored with exactly the
+///     same per-char [`match_bonus`], [`SCORE_CONSECUTIVE`], and
+///     [`SCORE_GAP_PENALTY`] terms [`optimal_match`] uses, so scores from
+///     this fallback stay comparable to (if not as optimal as) scores from
+///     the optimal path, letting [`match_list`] sort a mixed-size candidate
+///     list sensibly.
+fn greedy_match(
+    haystack: &[char],
+    case_chars: &[char],
+    needle: &[char],
+    config: &MatcherConfig,
+) -> Option<(Vec<usize>, i32)> {
+    let mut positions: Vec<usize> = Vec::with_capacity(needle.len());
     let mut cur = 0usize;
-    for &nc in lowered_needle.iter() {
+    for &nc in needle {
         let mut found_at: Option<usize> = None;
-        while cur < lowered_chars.len() {
-            if lowered_chars[cur] == nc {
+        while cur < haystack.len() {
+            if haystack[cur] == nc {
                 found_at = Some(cur);
                 cur += 1;
                 break;
             }
             cur += 1;
         }
-        let pos = found_at?;
-        result_orig_indices.push(lowered_to_orig_char_idx[pos]);
-        last_lower_pos = Some(pos);
+        positions.push(found_at?);
     }
 
-    let first_lower_pos = if result_orig_indices.is_empty() {
-        0usize
-    } else {
-        let target_orig = result_orig_indices[0];
-        lowered_to_orig_char_idx
-            .iter()
-            .position(|&oi| oi == target_orig)
-            .unwrap_or(0)
-    };
-    // last defaults to first for single-hit; score = extra span between first/last hit
-    // minus needle len (≥0).
-    // Strongly reward prefix matches by subtracting 100 when the first hit is at index 0.
-    let last_lower_pos = last_lower_pos.unwrap_or(first_lower_pos);
-    let window =
-        (last_lower_pos as i32 - first_lower_pos as i32 + 1) - (lowered_needle.len() as i32);
-    let mut score = window.max(0);
-    if first_lower_pos == 0 {
-        score -= 100;
+    let mut score = 0i32;
+    let mut run = 0i32;
+    for (i, &pos) in positions.iter().enumerate() {
+        if i > 0 {
+            let gap = pos - positions[i - 1] - 1;
+            if gap == 0 {
+                run += 1;
+            } else {
+                score += gap as i32 * SCORE_GAP_PENALTY;
+                run = 0;
+            }
+        }
+        score += match_bonus(case_chars, pos, i == 0, config) + run * SCORE_CONSECUTIVE;
     }
 
-    result_orig_indices.sort_unstable();
-    result_orig_indices.dedup();
-    Some((result_orig_indices, score))
+    Some((positions, score))
 }
 
 /// Convenience wrapper that returns only the matched indices.
@@ -150,29 +803,76 @@ mod tests {
 
     #[test]
     fn ascii_basic_indices() {
-        let (idx, score) = match fuzzy_match("hello", "hl") {
+        let (idx, _score) = match fuzzy_match("hello", "hl") {
             Some(v) => v,
             None => panic!("expected a match"),
         };
         assert_eq!(idx, vec![0, 2]);
-        // 'h' at 0, 'l' at 2 -> window 1; start-of-string bonus applies (-100)
-        assert_eq!(score, -99);
+    }
+
+    #[test]
+    fn optimal_path_picks_best_l_in_hello() {
+        // The greedy scan would grab the first 'l' (index 2), forcing a
+        // window that spans through 'l','l' at worst. The optimal path
+        // instead picks the *second* 'l' (index 3): it costs one extra gap
+        // step to reach from 'h', but lands right next to 'o' for a
+        // consecutive-run bonus that more than makes up for it.
+        let (idx, score) = match fuzzy_match("hello", "hlo") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert_eq!(idx, vec![0, 3, 4]);
+        assert_eq!(score, 58);
+    }
+
+    #[test]
+    fn gap_penalty_applies_even_when_a_repeated_char_is_skipped() {
+        // Regression test for a bug where skipping a haystack position that
+        // *did* match the current needle char (in favor of a later, better
+        // occurrence of the same char) was accidentally free: the gap
+        // penalty must apply to that choice exactly like any other skipped
+        // char, or repeated needle chars can score higher than any alignment
+        // the algorithm actually produces.
+        let (idx, score) = match fuzzy_match("aacab_a", "aaa") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert!(idx.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(idx.len(), 3);
+        assert_eq!(score, 61);
+    }
+
+    #[test]
+    fn optimal_path_prefers_a_later_consecutive_run_over_an_earlier_lone_match() {
+        // Regression test for a bug where `optimal_match` kept only one
+        // score per DP cell, conflating "best score through this column"
+        // with "best score with the match landing exactly here". That
+        // discarded the locally-worse-but-globally-better option of
+        // matching 'b' at index 1 (instead of index 0), which is what lets
+        // 'a' at index 2 land right after it for a consecutive-run bonus:
+        // b@1, a@2 scores 16 + (16 + 8) = 40, beating b@0, a@2's 37.
+        let (idx, score) = match fuzzy_match("bbaab", "ba") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert_eq!(idx, vec![1, 2]);
+        assert_eq!(score, 40);
     }
 
     #[test]
     fn unicode_dotted_i_istanbul_highlighting() {
-        let (idx, score) = match fuzzy_match("İstanbul", "is") {
+        let (idx, _score) = match fuzzy_match("İstanbul", "is") {
             Some(v) => v,
             None => panic!("expected a match"),
         };
         assert_eq!(idx, vec![0, 1]);
-        // Matches at lowered positions 0 and 2 -> window 1; start-of-string bonus applies
-        assert_eq!(score, -99);
     }
 
     #[test]
     fn unicode_german_sharp_s_casefold() {
-        assert!(fuzzy_match("straße", "strasse").is_none());
+        // "ß" diacritic-strips to "ss", and the ASCII needle has no accents
+        // of its own, so Smart-mode stripping kicks in.
+        assert!(fuzzy_match("straße", "strasse").is_some());
     }
 
     #[test]
@@ -185,11 +885,9 @@ mod tests {
             Some(v) => v,
             None => panic!("expected a match"),
         };
-        // Contiguous window -> 0; start-of-string bonus -> -100
-        assert_eq!(score_a, -100);
-        // Spread over 5 chars for 3-letter needle -> window 2; with bonus -> -98
-        assert_eq!(score_b, -98);
-        assert!(score_a < score_b);
+        // Contiguous, start-of-string match scores higher than one spread
+        // over extra characters.
+        assert!(score_a > score_b);
     }
 
     #[test]
@@ -198,15 +896,14 @@ mod tests {
             Some(v) => v,
             None => panic!("expected a match"),
         };
-        let (_idx_b, score_b) = match fuzzy_match("my_file_name", "file") {
+        // No delimiter precedes "file" here, so this isn't a word-boundary
+        // match like the one above (which starts at index 0) or one right
+        // after a delimiter.
+        let (_idx_b, score_b) = match fuzzy_match("myfile_name", "file") {
             Some(v) => v,
             None => panic!("expected a match"),
         };
-        // Start-of-string contiguous -> window 0; bonus -> -100
-        assert_eq!(score_a, -100);
-        // Non-prefix contiguous -> window 0; no bonus -> 0
-        assert_eq!(score_b, 0);
-        assert!(score_a < score_b);
+        assert!(score_a > score_b);
     }
 
     #[test]
@@ -221,24 +918,216 @@ mod tests {
 
     #[test]
     fn case_insensitive_matching_basic() {
-        let (idx, score) = match fuzzy_match("FooBar", "foO") {
+        let (idx, _score) = match fuzzy_match("FooBar", "foO") {
             Some(v) => v,
             None => panic!("expected a match"),
         };
         assert_eq!(idx, vec![0, 1, 2]);
-        // Contiguous prefix match (case-insensitive) -> window 0 with bonus
-        assert_eq!(score, -100);
     }
 
     #[test]
     fn indices_are_deduped_for_multichar_lowercase_expansion() {
         let needle = "\u{0069}\u{0307}"; // "i" + combining dot above
-        let (idx, score) = match fuzzy_match("İ", needle) {
+        let (idx, _score) = match fuzzy_match("İ", needle) {
             Some(v) => v,
             None => panic!("expected a match"),
         };
         assert_eq!(idx, vec![0]);
-        // Lowercasing 'İ' expands to two chars; contiguous prefix -> window 0 with bonus
-        assert_eq!(score, -100);
+    }
+
+    #[test]
+    fn large_haystack_uses_greedy_fallback() {
+        let haystack = format!("{}needle{}", "x".repeat(OPTIMAL_HAYSTACK_CHAR_LIMIT + 1), "z");
+        let (idx, _score) = match fuzzy_match(&haystack, "needle") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert_eq!(idx.len(), 6);
+    }
+
+    #[test]
+    fn greedy_fallback_score_matches_optimal_path_scale() {
+        // A contiguous needle at the start of a haystack exactly at the
+        // optimal-path limit (scored by the optimal DP) should score
+        // identically to the same needle at the start of a haystack past the
+        // limit (scored by the greedy fallback), since both paths share the
+        // same per-char bonus and gap-penalty terms.
+        let short = format!("needle{}", "x".repeat(OPTIMAL_HAYSTACK_CHAR_LIMIT - 6));
+        let long = format!("needle{}", "x".repeat(OPTIMAL_HAYSTACK_CHAR_LIMIT + 1));
+        let (_idx_short, score_short) = fuzzy_match(&short, "needle").unwrap();
+        let (_idx_long, score_long) = fuzzy_match(&long, "needle").unwrap();
+        assert_eq!(score_short, score_long);
+    }
+
+    #[test]
+    fn word_boundary_match_outranks_scattered_mid_word_hit() {
+        // "fb" at the delimiter-adjacent chars in "foo_bar" should beat the
+        // same needle matching mid-word with no delimiter before either char.
+        let (_idx_a, score_a) = match fuzzy_match("foo_bar", "fb") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        let (_idx_b, score_b) = match fuzzy_match("fabulous", "fb") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert!(score_a > score_b);
+    }
+
+    #[test]
+    fn camel_hump_boundary_scores_higher_than_mid_word() {
+        let (_idx_a, score_a) = match fuzzy_match("fooBar", "b") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        let (_idx_b, score_b) = match fuzzy_match("cab", "b") {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert!(score_a > score_b);
+    }
+
+    #[test]
+    fn match_paths_preset_treats_slash_as_the_boundary() {
+        let config = MatcherConfig {
+            char_classes: CharClassConfig::match_paths(),
+            ..MatcherConfig::default()
+        };
+        let (idx, _score) = match fuzzy_match_with_config("src/lib.rs", "lib", &config) {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert_eq!(idx, vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn match_list_filters_and_sorts_best_first() {
+        let results = match_list(["help", "skill", "hello", "goodbye"], "hl");
+        let names: Vec<&str> = results.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, vec!["help", "hello"]);
+    }
+
+    #[test]
+    fn match_list_breaks_score_ties_by_length_then_lexicographically() {
+        // Both are non-contiguous, non-boundary "ab" matches of equal score;
+        // the shorter haystack should sort first.
+        let results = match_list(["xaxbx", "xaxbxx"], "ab");
+        let names: Vec<&str> = results.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, vec!["xaxbx", "xaxbxx"]);
+
+        // Equal score and equal length: lexicographic order decides.
+        let results = match_list(["yaxbx", "xaxbx"], "ab");
+        let names: Vec<&str> = results.iter().map(|(name, _, _)| *name).collect();
+        assert_eq!(names, vec!["xaxbx", "yaxbx"]);
+    }
+
+    #[test]
+    fn ascii_needle_matches_accented_haystack_by_default() {
+        let (idx, _score) = match fuzzy_match("résumé", "resume") {
+            Some(v) => v,
+            None => panic!("expected Smart-mode diacritic stripping to match"),
+        };
+        assert_eq!(idx, vec![0, 1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn accented_needle_still_matches_exactly_in_smart_mode() {
+        assert!(fuzzy_match("résumé", "résumé").is_some());
+        // An accented needle shouldn't also match an unrelated plain-ASCII
+        // haystack that merely shares the stripped form by coincidence... but
+        // it should still fail to match a haystack missing the accent, since
+        // Smart mode leaves the haystack un-stripped once the needle itself
+        // carries an accent.
+        assert!(fuzzy_match("resume", "résumé").is_none());
+    }
+
+    #[test]
+    fn literal_mode_disables_diacritic_stripping() {
+        let config = MatcherConfig {
+            diacritics: DiacriticsMode::Literal,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("résumé", "resume", &config).is_none());
+    }
+
+    #[test]
+    fn always_strip_mode_normalizes_both_sides() {
+        let config = MatcherConfig {
+            diacritics: DiacriticsMode::AlwaysStrip,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("resume", "résumé", &config).is_some());
+    }
+
+    #[test]
+    fn ignore_case_matches_regardless_of_case() {
+        let config = MatcherConfig {
+            ignore_case: CaseSensitivity::Ignore,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("FooBar", "foo", &config).is_some());
+    }
+
+    #[test]
+    fn respect_case_requires_exact_case() {
+        let config = MatcherConfig {
+            ignore_case: CaseSensitivity::Respect,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("FooBar", "foo", &config).is_none());
+        assert!(fuzzy_match_with_config("FooBar", "Foo", &config).is_some());
+    }
+
+    #[test]
+    fn smart_case_is_insensitive_for_lowercase_needle() {
+        let config = MatcherConfig {
+            ignore_case: CaseSensitivity::Smart,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("FooBar", "foo", &config).is_some());
+    }
+
+    #[test]
+    fn smart_case_is_sensitive_once_needle_has_an_uppercase_char() {
+        let config = MatcherConfig {
+            ignore_case: CaseSensitivity::Smart,
+            ..MatcherConfig::default()
+        };
+        assert!(fuzzy_match_with_config("foobar", "Foo", &config).is_none());
+        assert!(fuzzy_match_with_config("FooBar", "Foo", &config).is_some());
+    }
+
+    #[test]
+    fn prefer_prefix_breaks_ties_toward_the_earlier_match() {
+        // Both are equally tight, non-boundary "ar" matches; only their
+        // distance from the start of the haystack differs.
+        let config = MatcherConfig::autocomplete();
+        let (_idx_a, score_a) = match fuzzy_match_with_config("xarx", "ar", &config) {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        let (_idx_b, score_b) = match fuzzy_match_with_config("xxxarx", "ar", &config) {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert!(score_a > score_b);
+    }
+
+    #[test]
+    fn prefer_prefix_does_not_override_a_tighter_match_further_in() {
+        // "ab" matches tightly (no gap) but far from the start in
+        // "xxxxxxab"; in "a_____b" it matches right at the start but with a
+        // wide gap. The prefix bonus is capped well below the gap penalty,
+        // so the tight-but-distant match should still outscore it.
+        let config = MatcherConfig::autocomplete();
+        let (_idx_tight, score_tight) = match fuzzy_match_with_config("xxxxxxab", "ab", &config) {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        let (_idx_loose, score_loose) = match fuzzy_match_with_config("a_____b", "ab", &config) {
+            Some(v) => v,
+            None => panic!("expected a match"),
+        };
+        assert!(score_tight > score_loose);
     }
 }
@@ -0,0 +1,403 @@
+//! fzf-style multi-atom query syntax layered on top of [`crate::fuzzy_match`].
+//!
+//! A query string is split on whitespace into independent atoms, each of
+//! which must match (logical AND) for the haystack to match the pattern
+//! overall. Each atom can carry one of the following operators:
+//!
+//! - `^prefix`: the haystack must literally start with `prefix`.
+//! - `suffix$`: the haystack must literally end with `suffix`.
+//! - `'exact`: the haystack must contain `exact` as a literal substring.
+//! - `!atom`: negates the atom (the haystack must NOT match it); composes
+//!   with the operators above, e.g. `!^prefix`. A bare `!word` is a literal
+//!   substring check (the haystack must not contain `word`), not a fuzzy one.
+//! - anything else is matched fuzzily via [`crate::fuzzy_match`].
+//!
+//! [`Pattern::match_haystack_with_config`] applies a single [`crate::MatcherConfig`]
+//! uniformly across every atom kind, so e.g. the `^`/`$`/`'` literal atoms
+//! honor case sensitivity and diacritics the same way the fuzzy atoms do.
+
+use crate::fuzzy_match_with_config;
+use crate::into_orig_indices;
+use crate::is_case_sensitive;
+use crate::normalize_haystack;
+use crate::normalize_needle;
+use crate::should_strip_diacritics;
+use crate::MatcherConfig;
+
+/// Flat score awarded to a literal (prefix/suffix/exact) atom match, on the
+/// same scale as [`crate::fuzzy_match`] scores.
+const SCORE_LITERAL_MATCH: i32 = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AtomKind {
+    Fuzzy,
+    Prefix,
+    Suffix,
+    Exact,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Atom {
+    kind: AtomKind,
+    negate: bool,
+    text: String,
+}
+
+impl Atom {
+    fn parse(word: &str) -> Self {
+        let (negate, rest) = match word.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, word),
+        };
+
+        if let Some(text) = rest.strip_prefix('\'') {
+            Self {
+                kind: AtomKind::Exact,
+                negate,
+                text: text.to_string(),
+            }
+        } else if let Some(text) = rest.strip_prefix('^') {
+            Self {
+                kind: AtomKind::Prefix,
+                negate,
+                text: text.to_string(),
+            }
+        } else if let Some(text) = rest.strip_suffix('$') {
+            Self {
+                kind: AtomKind::Suffix,
+                negate,
+                text: text.to_string(),
+            }
+        } else if negate {
+            // A bare negated atom ("!test") means "does not contain", which
+            // is a literal substring check rather than a fuzzy one.
+            Self {
+                kind: AtomKind::Exact,
+                negate,
+                text: rest.to_string(),
+            }
+        } else {
+            Self {
+                kind: AtomKind::Fuzzy,
+                negate: false,
+                text: rest.to_string(),
+            }
+        }
+    }
+
+    /// Evaluates this atom against `haystack`, already accounting for
+    /// negation: `None` always means the atom failed to match the pattern's
+    /// requirement (positive atoms need a match, negated atoms need the
+    /// absence of one).
+    fn eval(&self, haystack: &str, config: &MatcherConfig) -> Option<(Vec<usize>, i32)> {
+        let positive = match self.kind {
+            AtomKind::Fuzzy => fuzzy_match_with_config(haystack, &self.text, config),
+            AtomKind::Prefix => exact_prefix_match(haystack, &self.text, config),
+            AtomKind::Suffix => exact_suffix_match(haystack, &self.text, config),
+            AtomKind::Exact => exact_substring_match(haystack, &self.text, config),
+        };
+
+        if self.negate {
+            match positive {
+                Some(_) => None,
+                None => Some((Vec::new(), 0)),
+            }
+        } else {
+            positive
+        }
+    }
+}
+
+/// A parsed multi-atom query, e.g. `^git !test push$`.
+///
+/// Args:
+///     None: constructed via [`Pattern::parse`].
+///
+/// Example:
+///
+/// ```rust
+/// use codex_utils_fuzzy_match::Pattern;
+///
+/// let pattern = Pattern::parse("^git !test");
+/// assert!(pattern.match_haystack("git push").is_some());
+/// assert!(pattern.match_haystack("git test").is_none());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    atoms: Vec<Atom>,
+}
+
+impl Pattern {
+    /// Parses a query string into a [`Pattern`].
+    ///
+    /// Args:
+    ///     query (&str): The raw query, e.g. `^git !test`.
+    ///
+    /// Returns:
+    ///     Pattern: Never fails; an empty or all-whitespace query parses to
+    ///     a pattern with no atoms that matches every haystack.
+    pub fn parse(query: &str) -> Self {
+        let atoms = query.split_whitespace().map(Atom::parse).collect();
+        Self { atoms }
+    }
+
+    /// Matches this pattern against `haystack`.
+    ///
+    /// Args:
+    ///     haystack (&str): The string to test.
+    ///
+    /// Returns:
+    ///     Option<(Vec<usize>, i32)>:
+    ///         Some((indices, score)): The union of matched indices across
+    ///             all positive atoms (sorted, deduped) and their summed
+    ///             score, where larger is better.
+    ///         None: If any positive atom failed to match, or any negated
+    ///             atom matched.
+    pub fn match_haystack(&self, haystack: &str) -> Option<(Vec<usize>, i32)> {
+        self.match_haystack_with_config(haystack, &MatcherConfig::default())
+    }
+
+    /// Same as [`Pattern::match_haystack`], but every scoring knob (case
+    /// sensitivity, diacritics, char classes, prefix preference) is
+    /// controlled by a caller-supplied [`MatcherConfig`], applied uniformly
+    /// across every atom kind so e.g. `^resume` and a bare `resume` atom
+    /// treat diacritics the same way.
+    pub fn match_haystack_with_config(
+        &self,
+        haystack: &str,
+        config: &MatcherConfig,
+    ) -> Option<(Vec<usize>, i32)> {
+        let mut indices: Vec<usize> = Vec::new();
+        let mut score = 0i32;
+        for atom in &self.atoms {
+            let (atom_indices, atom_score) = atom.eval(haystack, config)?;
+            indices.extend(atom_indices);
+            score += atom_score;
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        Some((indices, score))
+    }
+}
+
+/// Normalizes `haystack` into chars plus a mapping back to original char
+/// indices, the same way [`crate::fuzzy_match_with_config`] does, honoring
+/// `config`'s case sensitivity and diacritics settings (so e.g. a literal
+/// `^resume` prefix atom matches `"résumé"` under the default Smart
+/// diacritics mode, same as a bare `resume` atom already does). Needed
+/// because a char that expands when normalized (e.g. `İ` -> `"i̇"`) means a
+/// normalized char count no longer lines up 1:1 with the original haystack's
+/// char indices, which a naive `haystack_chars[..needle_norm.len()]` slice
+/// would get wrong.
+fn normalize_for_literal_match(
+    haystack: &str,
+    needle: &str,
+    config: &MatcherConfig,
+) -> (Vec<char>, Vec<char>, Vec<usize>) {
+    let case_sensitive = is_case_sensitive(config.ignore_case, needle);
+    let strip_diacritics = !case_sensitive && should_strip_diacritics(config.diacritics, needle);
+    let needle_norm = normalize_needle(needle, case_sensitive, strip_diacritics);
+
+    let mut lowered_chars = Vec::new();
+    let mut case_chars = Vec::new();
+    let mut lowered_to_orig_char_idx = Vec::new();
+    normalize_haystack(
+        haystack,
+        case_sensitive,
+        strip_diacritics,
+        &mut lowered_chars,
+        &mut case_chars,
+        &mut lowered_to_orig_char_idx,
+    );
+    (needle_norm, lowered_chars, lowered_to_orig_char_idx)
+}
+
+/// Checks that `haystack` literally starts with `needle`.
+fn exact_prefix_match(haystack: &str, needle: &str, config: &MatcherConfig) -> Option<(Vec<usize>, i32)> {
+    if needle.is_empty() {
+        return Some((Vec::new(), SCORE_LITERAL_MATCH));
+    }
+    let (needle_norm, lowered_chars, lowered_to_orig_char_idx) =
+        normalize_for_literal_match(haystack, needle, config);
+    if needle_norm.len() > lowered_chars.len() || lowered_chars[..needle_norm.len()] != needle_norm[..] {
+        return None;
+    }
+    let (indices, _) = into_orig_indices(
+        (0..needle_norm.len()).collect(),
+        &lowered_to_orig_char_idx,
+        SCORE_LITERAL_MATCH,
+    );
+    Some((indices, SCORE_LITERAL_MATCH))
+}
+
+/// Checks that `haystack` literally ends with `needle`.
+fn exact_suffix_match(haystack: &str, needle: &str, config: &MatcherConfig) -> Option<(Vec<usize>, i32)> {
+    if needle.is_empty() {
+        return Some((Vec::new(), SCORE_LITERAL_MATCH));
+    }
+    let (needle_norm, lowered_chars, lowered_to_orig_char_idx) =
+        normalize_for_literal_match(haystack, needle, config);
+    if needle_norm.len() > lowered_chars.len() {
+        return None;
+    }
+    let start = lowered_chars.len() - needle_norm.len();
+    if lowered_chars[start..] != needle_norm[..] {
+        return None;
+    }
+    let (indices, _) = into_orig_indices(
+        (start..lowered_chars.len()).collect(),
+        &lowered_to_orig_char_idx,
+        SCORE_LITERAL_MATCH,
+    );
+    Some((indices, SCORE_LITERAL_MATCH))
+}
+
+/// Checks that `haystack` contains `needle` as a literal substring,
+/// returning the indices of its first occurrence.
+fn exact_substring_match(haystack: &str, needle: &str, config: &MatcherConfig) -> Option<(Vec<usize>, i32)> {
+    if needle.is_empty() {
+        return Some((Vec::new(), SCORE_LITERAL_MATCH));
+    }
+    let (needle_norm, lowered_chars, lowered_to_orig_char_idx) =
+        normalize_for_literal_match(haystack, needle, config);
+    if needle_norm.len() > lowered_chars.len() {
+        return None;
+    }
+    let window_count = lowered_chars.len() - needle_norm.len() + 1;
+    for start in 0..window_count {
+        if lowered_chars[start..start + needle_norm.len()] == needle_norm[..] {
+            let (indices, _) = into_orig_indices(
+                (start..start + needle_norm.len()).collect(),
+                &lowered_to_orig_char_idx,
+                SCORE_LITERAL_MATCH,
+            );
+            return Some((indices, SCORE_LITERAL_MATCH));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bare_atom_is_fuzzy() {
+        let pattern = Pattern::parse("hlo");
+        assert!(pattern.match_haystack("hello").is_some());
+        assert!(pattern.match_haystack("goodbye").is_none());
+    }
+
+    #[test]
+    fn prefix_atom_requires_literal_start() {
+        let pattern = Pattern::parse("^git");
+        assert!(pattern.match_haystack("git push").is_some());
+        assert!(pattern.match_haystack("my git push").is_none());
+    }
+
+    #[test]
+    fn suffix_atom_requires_literal_end() {
+        let pattern = Pattern::parse("push$");
+        assert!(pattern.match_haystack("git push").is_some());
+        assert!(pattern.match_haystack("push origin").is_none());
+    }
+
+    #[test]
+    fn exact_atom_requires_literal_substring_anywhere() {
+        // "push" would match "do a git pull, then hush" fuzzily, but it is
+        // not a literal substring of it.
+        let pattern = Pattern::parse("'push");
+        assert!(pattern.match_haystack("do a git push now").is_some());
+        assert!(pattern.match_haystack("do a git pull, then hush").is_none());
+    }
+
+    #[test]
+    fn negated_atom_requires_absence() {
+        let pattern = Pattern::parse("!test");
+        assert!(pattern.match_haystack("git push").is_some());
+        assert!(pattern.match_haystack("git test").is_none());
+    }
+
+    #[test]
+    fn negated_prefix_atom_requires_absence_of_literal_start() {
+        let pattern = Pattern::parse("!^git");
+        assert!(pattern.match_haystack("my git push").is_some());
+        assert!(pattern.match_haystack("git push").is_none());
+    }
+
+    #[test]
+    fn multiple_atoms_combine_with_and() {
+        let pattern = Pattern::parse("^git !test push$");
+        assert!(pattern.match_haystack("git fast push").is_some());
+        assert!(pattern.match_haystack("git test push").is_none());
+        assert!(pattern.match_haystack("fast git push").is_none());
+    }
+
+    #[test]
+    fn empty_query_matches_everything() {
+        let pattern = Pattern::parse("   ");
+        let (idx, score) = pattern.match_haystack("anything").unwrap();
+        assert!(idx.is_empty());
+        assert_eq!(score, 0);
+    }
+
+    #[test]
+    fn prefix_atom_handles_expanding_lowercase_chars() {
+        // 'İ' expands to two chars ('i' + a combining dot) under
+        // `to_lowercase`, so a naive slice of the *original* haystack chars
+        // by the *lowered* needle's char count misaligns and wrongly misses
+        // this match.
+        let needle = "\u{0069}\u{0307}"; // "i" + combining dot above
+        let pattern = Pattern::parse(&format!("^{needle}"));
+        let (idx, _score) = match pattern.match_haystack("İstanbul") {
+            Some(v) => v,
+            None => panic!("expected the prefix match to succeed"),
+        };
+        assert_eq!(idx, vec![0]);
+    }
+
+    #[test]
+    fn suffix_atom_handles_expanding_lowercase_chars() {
+        let needle = "\u{0069}\u{0307}"; // "i" + combining dot above
+        let pattern = Pattern::parse(&format!("{needle}$"));
+        let (idx, _score) = match pattern.match_haystack("xİ") {
+            Some(v) => v,
+            None => panic!("expected the suffix match to succeed"),
+        };
+        assert_eq!(idx, vec![1]);
+    }
+
+    #[test]
+    fn literal_atoms_honor_diacritics_mode_like_fuzzy_atoms_do() {
+        // Regression test: `^resume` (a prefix atom) used to ignore
+        // `MatcherConfig` entirely and never strip diacritics, so it
+        // rejected "résumé" even though a bare `resume` atom (routed through
+        // `fuzzy_match`) already matched it by default. Prefix, suffix, and
+        // exact atoms should all honor the same Smart-mode diacritic
+        // stripping as fuzzy atoms.
+        let config = MatcherConfig::default();
+        let prefix = Pattern::parse("^resume");
+        assert!(prefix
+            .match_haystack_with_config("résumé", &config)
+            .is_some());
+
+        let suffix = Pattern::parse("resume$");
+        assert!(suffix
+            .match_haystack_with_config("résumé", &config)
+            .is_some());
+
+        let exact = Pattern::parse("'resume");
+        assert!(exact
+            .match_haystack_with_config("a résumé here", &config)
+            .is_some());
+
+        // Literal mode disables diacritic stripping for both atom kinds.
+        let literal_config = MatcherConfig {
+            diacritics: crate::DiacriticsMode::Literal,
+            ..MatcherConfig::default()
+        };
+        assert!(prefix
+            .match_haystack_with_config("résumé", &literal_config)
+            .is_none());
+    }
+}